@@ -1,22 +1,19 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 /// column key type
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub enum ColumnKey {
+    #[default]
     NotKey,
     Primary,
     Unique,
     Multiple,
 }
 
-impl Default for ColumnKey {
-    fn default() -> Self {
-        ColumnKey::NotKey
-    }
-}
-
 /// column type, variant can have specific size, e.g.: Int(i32)
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub enum ColumnType {
     Bool,
     Int,
@@ -27,16 +24,13 @@ pub enum ColumnType {
     DateTime,
     Timestamp,
     Char,
+    #[default]
     VarChar,
     Text,
     Json,
     Binary,
-}
-
-impl Default for ColumnType {
-    fn default() -> Self {
-        ColumnType::VarChar
-    }
+    /// verbatim SQL type overriding the default mapping, e.g.: `NUMERIC(18,4)`
+    Custom(String),
 }
 
 /// a column mainly contains four arguments
@@ -48,10 +42,102 @@ pub struct Column {
     pub key: Option<ColumnKey>,
 }
 
+/// table name with an optional schema, e.g.: `analytics.events`.
+/// an unqualified table is written as a plain string in JSON.
+/// names are stored as given and never quoted, dialect-correct quoting is up to
+/// whatever renders SQL from this model
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(from = "TableRefRepr", into = "TableRefRepr")]
+pub struct TableRef {
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl TableRef {
+    pub fn new(name: &str) -> Self {
+        TableRef {
+            schema: None,
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn with_schema(schema: &str, name: &str) -> Self {
+        TableRef {
+            schema: Some(schema.to_owned()),
+            name: name.to_owned(),
+        }
+    }
+}
+
+impl From<&str> for TableRef {
+    fn from(v: &str) -> Self {
+        TableRef::new(v)
+    }
+}
+
+impl From<String> for TableRef {
+    fn from(v: String) -> Self {
+        TableRef {
+            schema: None,
+            name: v,
+        }
+    }
+}
+
+/// a display-only label (`schema.name`) for logs and messages, it is unquoted and
+/// must not be spliced into SQL
+impl fmt::Display for TableRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.schema {
+            Some(schema) => write!(f, "{}.{}", schema, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TableRefRepr {
+    Name(String),
+    Qualified(QualifiedTableRepr),
+}
+
+// unknown keys are rejected so that untagged enums holding a `TableRef`, such as
+// `SelectFrom`, can fall through to their other variants
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QualifiedTableRepr {
+    schema: Option<String>,
+    name: String,
+}
+
+impl From<TableRefRepr> for TableRef {
+    fn from(v: TableRefRepr) -> Self {
+        match v {
+            TableRefRepr::Name(name) => TableRef { schema: None, name },
+            TableRefRepr::Qualified(QualifiedTableRepr { schema, name }) => {
+                TableRef { schema, name }
+            }
+        }
+    }
+}
+
+impl From<TableRef> for TableRefRepr {
+    fn from(v: TableRef) -> Self {
+        match v.schema {
+            Some(schema) => TableRefRepr::Qualified(QualifiedTableRepr {
+                schema: Some(schema),
+                name: v.name,
+            }),
+            None => TableRefRepr::Name(v.name),
+        }
+    }
+}
+
 /// table with its' name, columns and optional foreign key
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Table {
-    pub name: String,
+    pub name: TableRef,
     pub columns: Vec<Column>,
     pub foreign_key: Option<ForeignKey>,
 }
@@ -74,33 +160,28 @@ pub struct Order {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Index {
     pub name: String,
-    pub table: String,
+    pub table: TableRef,
     pub columns: Vec<Order>,
 }
 
 /// foreign key direction
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ForeignKeyDir {
-    pub table: String,
+    pub table: TableRef,
     pub column: String,
 }
 
 /// foreign key action
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub enum ForeignKeyAction {
     Restrict,
     Cascade,
     SetNull,
+    #[default]
     NoAction,
     SetDefault,
 }
 
-impl Default for ForeignKeyAction {
-    fn default() -> Self {
-        ForeignKeyAction::NoAction
-    }
-}
-
 /// foreign key with its' unique name, from & to table relations, and actions
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ForeignKey {
@@ -147,12 +228,30 @@ impl From<i32> for DataEnum {
     }
 }
 
+impl From<i64> for DataEnum {
+    fn from(v: i64) -> Self {
+        DataEnum::Integer(v)
+    }
+}
+
 impl From<f32> for DataEnum {
     fn from(v: f32) -> Self {
         DataEnum::Float(v as f64)
     }
 }
 
+impl From<f64> for DataEnum {
+    fn from(v: f64) -> Self {
+        DataEnum::Float(v)
+    }
+}
+
+impl From<bool> for DataEnum {
+    fn from(v: bool) -> Self {
+        DataEnum::Bool(v)
+    }
+}
+
 impl From<String> for DataEnum {
     fn from(v: String) -> Self {
         DataEnum::String(v)
@@ -165,19 +264,25 @@ impl From<&str> for DataEnum {
     }
 }
 
-/// column name, can be alias. used it in `select`
+/// column name, can be alias. used it in `select`.
+/// `Expr` is a raw fragment such as `price * qty AS total`, it is NOT escaped,
+/// and is written as `{"Expr": "..."}` in JSON
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-#[serde(untagged)]
 pub enum ColumnAlias {
+    Expr(String),
+    #[serde(untagged)]
     Simple(String),
+    #[serde(untagged)]
     Alias((String, String)),
 }
 
 impl ColumnAlias {
-    pub fn name(&self) -> String {
+    /// source column name, `None` for a raw `Expr` which has no single column
+    pub fn name(&self) -> Option<String> {
         match self {
-            ColumnAlias::Simple(s) => s.to_owned(),
-            ColumnAlias::Alias((s, _)) => s.to_owned(),
+            ColumnAlias::Simple(s) => Some(s.to_owned()),
+            ColumnAlias::Alias((s, _)) => Some(s.to_owned()),
+            ColumnAlias::Expr(_) => None,
         }
     }
 }
@@ -190,7 +295,7 @@ mod tests_common {
     #[test]
     fn convert() {
         let table = Table {
-            name: "test".to_string(),
+            name: "test".into(),
             columns: vec![
                 Column {
                     name: "id".to_string(),
@@ -211,4 +316,53 @@ mod tests_common {
 
         assert_eq!(deserialized, table);
     }
+
+    #[test]
+    fn table_ref() {
+        let plain = TableRef::new("events");
+        let qualified = TableRef::with_schema("analytics", "events");
+
+        assert_eq!(serde_json::to_string(&plain).unwrap(), "\"events\"");
+        assert_eq!(
+            serde_json::to_string(&qualified).unwrap(),
+            "{\"schema\":\"analytics\",\"name\":\"events\"}"
+        );
+        assert_eq!(
+            serde_json::from_str::<TableRef>("{\"name\":\"events\"}").unwrap(),
+            plain
+        );
+        assert_eq!(qualified.to_string(), "analytics.events");
+        assert!(serde_json::from_str::<TableRef>(
+            "{\"schema\":\"analytics\",\"name\":\"events\",\"alias\":\"e\"}"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn custom_column_type() {
+        let column = Column {
+            name: "amount".to_string(),
+            col_type: ColumnType::Custom("NUMERIC(18,4)".to_string()),
+            ..Default::default()
+        };
+
+        let serialized = serde_json::to_string(&column).unwrap();
+
+        assert_eq!(
+            serialized,
+            "{\"name\":\"amount\",\"col_type\":{\"Custom\":\"NUMERIC(18,4)\"},\"null\":null,\"key\":null}"
+        );
+        assert_eq!(serde_json::from_str::<Column>(&serialized).unwrap(), column);
+    }
+
+    #[test]
+    fn column_alias_name() {
+        let simple = ColumnAlias::Simple("c1".to_string());
+        let alias = ColumnAlias::Alias(("c2".to_string(), "c2_t".to_string()));
+        let expr = ColumnAlias::Expr("price * qty AS total".to_string());
+
+        assert_eq!(simple.name(), Some("c1".to_string()));
+        assert_eq!(alias.name(), Some("c2".to_string()));
+        assert_eq!(expr.name(), None);
+    }
 }
@@ -13,7 +13,10 @@ pub use schema::table_list::*;
 pub use schema::table_rename::*;
 pub use schema::table_truncate::*;
 
+#[allow(unused_imports)]
 pub use query::delete::*;
+#[allow(unused_imports)]
 pub use query::insert::*;
 pub use query::select::*;
+#[allow(unused_imports)]
 pub use query::update::*;
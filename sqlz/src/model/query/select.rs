@@ -1,17 +1,192 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
-use crate::{ColumnAlias, DataEnum, Order};
+use crate::{ColumnAlias, DataEnum, Order, OrderType, TableRef};
 
-// TODO: Join & GroupBy
+// TODO: GroupBy
 
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
 pub struct Select {
-    pub table: String,
+    pub table: SelectFrom,
     pub columns: Vec<ColumnAlias>,
+    pub windows: Option<Vec<Window>>,
+    pub joins: Option<Vec<Join>>,
     pub filter: Option<Vec<Expression>>,
     pub order: Option<Vec<Order>>,
     pub limit: Option<u64>,
     pub offset: Option<u64>,
+    /// named selections emitted as `WITH name AS (...)` ahead of this query
+    pub ctes: Option<Vec<Cte>>,
+}
+
+/// where rows come from, used by both `Select` and `Join`. a table is written as
+/// before in JSON (a plain string or `{"schema": .., "name": ..}`), an aliased table
+/// as `{"table": .., "alias": ..}`, and a subquery as `{"select": .., "alias": ..}`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SelectFrom {
+    Table(TableRef),
+    AliasedTable { table: TableRef, alias: String },
+    Subquery { select: Box<Select>, alias: String },
+}
+
+impl SelectFrom {
+    /// `table AS alias`, e.g. to join the same table twice
+    pub fn aliased<T: Into<TableRef>>(table: T, alias: &str) -> Self {
+        SelectFrom::AliasedTable {
+            table: table.into(),
+            alias: alias.to_owned(),
+        }
+    }
+}
+
+impl Default for SelectFrom {
+    fn default() -> Self {
+        SelectFrom::Table(TableRef::default())
+    }
+}
+
+impl From<TableRef> for SelectFrom {
+    fn from(v: TableRef) -> Self {
+        SelectFrom::Table(v)
+    }
+}
+
+impl From<&str> for SelectFrom {
+    fn from(v: &str) -> Self {
+        SelectFrom::Table(v.into())
+    }
+}
+
+/// common table expression, can be referred by its name as a table
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Cte {
+    pub name: String,
+    pub select: Select,
+}
+
+impl Select {
+    /// `SELECT columns FROM (subquery) AS alias`
+    pub fn from_subquery(subquery: Select, alias: &str, columns: Vec<ColumnAlias>) -> Self {
+        Select {
+            table: SelectFrom::Subquery {
+                select: Box::new(subquery),
+                alias: alias.to_owned(),
+            },
+            columns,
+            ..Default::default()
+        }
+    }
+
+    /// check the filter of this query, of its subquery sources and of its ctes,
+    /// see `Expression::validate`, and that every join has an `on` pair
+    pub fn validate(&self) -> Result<(), ExpressionError> {
+        if let Some(filter) = &self.filter {
+            Expression::validate(filter)?;
+        }
+        for join in self.joins.iter().flatten() {
+            if join.on.is_empty() {
+                return Err(ExpressionError::EmptyJoinOn);
+            }
+            if let SelectFrom::Subquery { select, .. } = &join.table {
+                select.validate()?;
+            }
+        }
+        if let SelectFrom::Subquery { select, .. } = &self.table {
+            select.validate()?;
+        }
+        for cte in self.ctes.iter().flatten() {
+            cte.select.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// keyset pagination, the page right after `last_seen` (`None` for the first page):
+    /// `WHERE (filter) AND index_column > last_seen ORDER BY index_column LIMIT page_size`.
+    /// `index_column` must be unique, otherwise rows sharing the last seen value are
+    /// skipped. any existing `order` is replaced and `offset` is cleared
+    pub fn paginate_after(
+        &self,
+        index_column: &str,
+        last_seen: Option<DataEnum>,
+        page_size: u64,
+    ) -> Self {
+        let mut page = self.clone();
+
+        if matches!(&page.filter, Some(filter) if filter.is_empty()) {
+            page.filter = None;
+        }
+        if let Some(value) = last_seen {
+            let filter = ExpressionBuilder::from(page.filter.take().unwrap_or_default());
+            page.filter = Some(filter.and(Expr::col(index_column).gt(value)).0);
+        }
+        page.order = Some(vec![Order {
+            name: index_column.to_owned(),
+            order: Some(OrderType::Asc),
+        }]);
+        page.limit = Some(page_size);
+        page.offset = None;
+
+        page
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Count(String),
+    Sum(String),
+    Avg(String),
+    Min(String),
+    Max(String),
+    /// `LAG(column, offset, default)`, offset defaults to 1 and default to NULL
+    Lag {
+        column: String,
+        offset: Option<u64>,
+        default: Option<DataEnum>,
+    },
+    /// `LEAD(column, offset, default)`, offset defaults to 1 and default to NULL
+    Lead {
+        column: String,
+        offset: Option<u64>,
+        default: Option<DataEnum>,
+    },
+}
+
+/// a window column, e.g.: `ROW_NUMBER() OVER (PARTITION BY a ORDER BY b) AS rn`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Window {
+    pub function: WindowFunction,
+    pub partition_by: Vec<String>,
+    pub order_by: Vec<Order>,
+    pub alias: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+/// `left` and `right` are column names, which can be qualified by table, e.g.: `t1.id`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct JoinOn {
+    pub left: String,
+    pub right: String,
+}
+
+/// a joined table, aliased table or subquery, all of its `on` pairs are combined by `AND`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Join {
+    pub table: SelectFrom,
+    pub join_type: JoinType,
+    pub on: Vec<JoinOn>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -35,8 +210,13 @@ pub enum Equation {
     Less(DataEnum),
     LessEqual(DataEnum),
     In(Vec<DataEnum>),
+    NotIn(Vec<DataEnum>),
     Between((DataEnum, DataEnum)),
     Like(String),
+    NotLike(String),
+    IsNull,
+    IsNotNull,
+    InSubquery(Box<Select>),
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -45,14 +225,252 @@ pub struct Condition {
     pub equation: Equation,
 }
 
+/// `Raw` is a dialect-specific fragment used as a condition, it is NOT escaped,
+/// and is written as `{"Raw": "..."}` in JSON
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
-#[serde(untagged)]
 pub enum Expression {
+    Raw(String),
+    #[serde(untagged)]
     Conjunction(Conjunction),
+    #[serde(untagged)]
     Simple(Condition),
+    #[serde(untagged)]
     Nest(Vec<Expression>),
 }
 
+/// reasons why a filter is not a legal sequence of conditions and conjunctions
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionError {
+    Empty,
+    LeadingConjunction,
+    TrailingConjunction,
+    ConsecutiveConjunctions,
+    MissingConjunction,
+    EmptyList,
+    EmptyJoinOn,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            ExpressionError::Empty => "empty expression",
+            ExpressionError::LeadingConjunction => "expression starts with a conjunction",
+            ExpressionError::TrailingConjunction => "expression ends with a conjunction",
+            ExpressionError::ConsecutiveConjunctions => "two conjunctions in a row",
+            ExpressionError::MissingConjunction => "two conditions without a conjunction",
+            ExpressionError::EmptyList => "`In` or `NotIn` without any value",
+            ExpressionError::EmptyJoinOn => "join without any `on` pair",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+impl Expression {
+    /// start an empty filter builder, see `Expr`
+    pub fn builder() -> ExpressionBuilder {
+        ExpressionBuilder::default()
+    }
+
+    /// a legal filter alternates conditions and conjunctions, and every nested
+    /// filter, including those of `InSubquery` selections, is legal as well
+    pub fn validate(exprs: &[Expression]) -> Result<(), ExpressionError> {
+        if exprs.is_empty() {
+            return Err(ExpressionError::Empty);
+        }
+
+        let mut expect_condition = true;
+        for (i, expr) in exprs.iter().enumerate() {
+            match expr {
+                Expression::Conjunction(_) => {
+                    if i == 0 {
+                        return Err(ExpressionError::LeadingConjunction);
+                    }
+                    if expect_condition {
+                        return Err(ExpressionError::ConsecutiveConjunctions);
+                    }
+                    expect_condition = true;
+                }
+                Expression::Simple(_) | Expression::Nest(_) | Expression::Raw(_) => {
+                    if !expect_condition {
+                        return Err(ExpressionError::MissingConjunction);
+                    }
+                    match expr {
+                        Expression::Nest(nested) => Expression::validate(nested)?,
+                        Expression::Simple(Condition { equation, .. }) => match equation {
+                            Equation::In(values) | Equation::NotIn(values) if values.is_empty() => {
+                                return Err(ExpressionError::EmptyList)
+                            }
+                            Equation::InSubquery(select) => select.validate()?,
+                            _ => {}
+                        },
+                        _ => {}
+                    }
+                    expect_condition = false;
+                }
+            }
+        }
+
+        if expect_condition {
+            return Err(ExpressionError::TrailingConjunction);
+        }
+
+        Ok(())
+    }
+}
+
+/// entry point of the fluent filter builder, e.g.:
+/// `Expr::col("age").gt(30).and(Expr::col("name").like("J%"))`
+pub struct Expr;
+
+impl Expr {
+    pub fn col(name: &str) -> ExprColumn {
+        ExprColumn(name.to_owned())
+    }
+
+    /// unescaped condition, see `Expression::Raw`
+    pub fn raw(sql: &str) -> ExpressionBuilder {
+        ExpressionBuilder(vec![Expression::Raw(sql.to_owned())])
+    }
+}
+
+/// a column waiting for its equation
+pub struct ExprColumn(String);
+
+impl ExprColumn {
+    fn equation(self, equation: Equation) -> ExpressionBuilder {
+        ExpressionBuilder(vec![Expression::Simple(Condition {
+            column: self.0,
+            equation,
+        })])
+    }
+
+    pub fn eq<T: Into<DataEnum>>(self, value: T) -> ExpressionBuilder {
+        self.equation(Equation::Equal(value.into()))
+    }
+
+    pub fn ne<T: Into<DataEnum>>(self, value: T) -> ExpressionBuilder {
+        self.equation(Equation::NotEqual(value.into()))
+    }
+
+    pub fn gt<T: Into<DataEnum>>(self, value: T) -> ExpressionBuilder {
+        self.equation(Equation::Greater(value.into()))
+    }
+
+    pub fn ge<T: Into<DataEnum>>(self, value: T) -> ExpressionBuilder {
+        self.equation(Equation::GreaterEqual(value.into()))
+    }
+
+    pub fn lt<T: Into<DataEnum>>(self, value: T) -> ExpressionBuilder {
+        self.equation(Equation::Less(value.into()))
+    }
+
+    pub fn le<T: Into<DataEnum>>(self, value: T) -> ExpressionBuilder {
+        self.equation(Equation::LessEqual(value.into()))
+    }
+
+    pub fn is_in<I, T>(self, values: I) -> ExpressionBuilder
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<DataEnum>,
+    {
+        self.equation(Equation::In(values.into_iter().map(Into::into).collect()))
+    }
+
+    pub fn not_in<I, T>(self, values: I) -> ExpressionBuilder
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<DataEnum>,
+    {
+        self.equation(Equation::NotIn(
+            values.into_iter().map(Into::into).collect(),
+        ))
+    }
+
+    pub fn between<T: Into<DataEnum>>(self, low: T, high: T) -> ExpressionBuilder {
+        self.equation(Equation::Between((low.into(), high.into())))
+    }
+
+    pub fn like(self, pattern: &str) -> ExpressionBuilder {
+        self.equation(Equation::Like(pattern.to_owned()))
+    }
+
+    pub fn not_like(self, pattern: &str) -> ExpressionBuilder {
+        self.equation(Equation::NotLike(pattern.to_owned()))
+    }
+
+    pub fn is_null(self) -> ExpressionBuilder {
+        self.equation(Equation::IsNull)
+    }
+
+    pub fn is_not_null(self) -> ExpressionBuilder {
+        self.equation(Equation::IsNotNull)
+    }
+
+    pub fn in_subquery(self, select: Select) -> ExpressionBuilder {
+        self.equation(Equation::InSubquery(Box::new(select)))
+    }
+}
+
+/// accumulates a filter, nesting operands so that mixed `and`/`or` chains keep
+/// the order they were written in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpressionBuilder(Vec<Expression>);
+
+/// continue from an existing filter, e.g. one deserialized from JSON
+impl From<Vec<Expression>> for ExpressionBuilder {
+    fn from(v: Vec<Expression>) -> Self {
+        ExpressionBuilder(v)
+    }
+}
+
+impl ExpressionBuilder {
+    pub fn and(self, other: ExpressionBuilder) -> Self {
+        self.join(Conjunction::AND, other)
+    }
+
+    pub fn or(self, other: ExpressionBuilder) -> Self {
+        self.join(Conjunction::OR, other)
+    }
+
+    fn join(self, conjunction: Conjunction, other: ExpressionBuilder) -> Self {
+        if other.0.is_empty() {
+            return self;
+        }
+        if self.0.is_empty() {
+            return other;
+        }
+
+        let mut exprs = Self::group(self.0, &conjunction);
+        exprs.push(Expression::Conjunction(conjunction.clone()));
+        exprs.extend(Self::group(other.0, &conjunction));
+
+        ExpressionBuilder(exprs)
+    }
+
+    // an operand joined by a different conjunction, or holding a raw fragment that
+    // may carry conjunctions of its own, becomes a `Nest`
+    fn group(exprs: Vec<Expression>, conjunction: &Conjunction) -> Vec<Expression> {
+        let nest = exprs.iter().any(|e| match e {
+            Expression::Conjunction(c) => c != conjunction,
+            Expression::Raw(_) => true,
+            _ => false,
+        });
+
+        if nest {
+            vec![Expression::Nest(exprs)]
+        } else {
+            exprs
+        }
+    }
+
+    pub fn build(self) -> Result<Vec<Expression>, ExpressionError> {
+        Expression::validate(&self.0)?;
+        Ok(self.0)
+    }
+}
+
 #[cfg(test)]
 mod tests_select {
     use super::*;
@@ -83,7 +501,7 @@ mod tests_select {
             ]),
         ];
         let selection = Select {
-            table: "sqlz".to_owned(),
+            table: "sqlz".into(),
             columns: vec![
                 ColumnAlias::Simple("c1".to_owned()),
                 ColumnAlias::Alias(("c2".to_owned(), "c2_t".to_owned())),
@@ -92,6 +510,7 @@ mod tests_select {
             order: None,
             limit: Some(10),
             offset: Some(20),
+            ..Default::default()
         };
 
         let cvt = serde_json::to_string(&selection).unwrap();
@@ -99,6 +518,8 @@ mod tests_select {
         {
             "table": "sqlz",
             "columns":["c1",["c2","c2_t"]],
+            "windows":null,
+            "joins":null,
             "filter":[
                 {"column":"c1","equation":{"Between":[23,25]}},
                 "OR",
@@ -112,11 +533,494 @@ mod tests_select {
             ],
             "order":null,
             "limit":10,
-            "offset":20
+            "offset":20,
+            "ctes":null
         }"##;
 
-        let res = "{\"table\":\"sqlz\",\"columns\":[\"c1\",[\"c2\",\"c2_t\"]],\"filter\":[{\"column\":\"c1\",\"equation\":{\"Between\":[23,25]}},\"OR\",{\"column\":\"c2\",\"equation\":{\"Equal\":1}},\"AND\",[{\"column\":\"c3\",\"equation\":{\"Greater\":23}},\"AND\",{\"column\":\"c4\",\"equation\":{\"In\":[\"T1\",\"T2\"]}}]],\"order\":null,\"limit\":10,\"offset\":20}";
+        let res = "{\"table\":\"sqlz\",\"columns\":[\"c1\",[\"c2\",\"c2_t\"]],\"windows\":null,\"joins\":null,\"filter\":[{\"column\":\"c1\",\"equation\":{\"Between\":[23,25]}},\"OR\",{\"column\":\"c2\",\"equation\":{\"Equal\":1}},\"AND\",[{\"column\":\"c3\",\"equation\":{\"Greater\":23}},\"AND\",{\"column\":\"c4\",\"equation\":{\"In\":[\"T1\",\"T2\"]}}]],\"order\":null,\"limit\":10,\"offset\":20,\"ctes\":null}";
 
         assert_eq!(cvt, res);
     }
+
+    #[test]
+    fn builder() {
+        let filter = Expression::builder()
+            .and(Expr::col("c1").between(23, 25))
+            .or(Expr::col("c2").eq(1))
+            .and(
+                Expr::col("c3")
+                    .gt(23)
+                    .and(Expr::col("c4").is_in(vec!["T1", "T2"])),
+            )
+            .build()
+            .unwrap();
+
+        let expected = vec![
+            Expression::Nest(vec![
+                Expression::Simple(Condition {
+                    column: "c1".to_owned(),
+                    equation: Equation::Between((DataEnum::Integer(23), DataEnum::Integer(25))),
+                }),
+                Expression::Conjunction(Conjunction::OR),
+                Expression::Simple(Condition {
+                    column: "c2".to_owned(),
+                    equation: Equation::Equal(DataEnum::Integer(1)),
+                }),
+            ]),
+            Expression::Conjunction(Conjunction::AND),
+            Expression::Simple(Condition {
+                column: "c3".to_owned(),
+                equation: Equation::Greater(DataEnum::Integer(23)),
+            }),
+            Expression::Conjunction(Conjunction::AND),
+            Expression::Simple(Condition {
+                column: "c4".to_owned(),
+                equation: Equation::In(vec![DataEnum::from("T1"), DataEnum::from("T2")]),
+            }),
+        ];
+
+        assert_eq!(filter, expected);
+
+        let extended = ExpressionBuilder::from(filter)
+            .and(Expr::col("c5").is_null())
+            .build()
+            .unwrap();
+        assert_eq!(extended.len(), 7);
+        assert_eq!(extended[..5], expected[..]);
+    }
+
+    #[test]
+    fn validation() {
+        let cond = || {
+            Expression::Simple(Condition {
+                column: "c1".to_owned(),
+                equation: Equation::Like("J%".to_owned()),
+            })
+        };
+        let and = || Expression::Conjunction(Conjunction::AND);
+
+        assert_eq!(Expression::builder().build(), Err(ExpressionError::Empty));
+        assert_eq!(
+            Expression::validate(&[and(), cond()]),
+            Err(ExpressionError::LeadingConjunction)
+        );
+        assert_eq!(
+            Expression::validate(&[cond(), and()]),
+            Err(ExpressionError::TrailingConjunction)
+        );
+        assert_eq!(
+            Expression::validate(&[cond(), and(), and(), cond()]),
+            Err(ExpressionError::ConsecutiveConjunctions)
+        );
+        assert_eq!(
+            Expression::validate(&[cond(), cond()]),
+            Err(ExpressionError::MissingConjunction)
+        );
+        assert_eq!(
+            Expression::validate(&[cond(), and(), Expression::Nest(vec![])]),
+            Err(ExpressionError::Empty)
+        );
+        assert_eq!(
+            Expr::col("id").is_in(Vec::<i32>::new()).build(),
+            Err(ExpressionError::EmptyList)
+        );
+        assert_eq!(
+            Expr::col("c1")
+                .gt(1)
+                .and(Expr::col("id").not_in(Vec::<i32>::new()))
+                .build(),
+            Err(ExpressionError::EmptyList)
+        );
+
+        let malformed = Select {
+            table: "vip".into(),
+            filter: Some(vec![and()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            Expr::col("id").in_subquery(malformed.clone()).build(),
+            Err(ExpressionError::LeadingConjunction)
+        );
+        assert_eq!(
+            Select::from_subquery(malformed.clone(), "v", vec![]).validate(),
+            Err(ExpressionError::LeadingConjunction)
+        );
+        let with_cte = Select {
+            table: "v".into(),
+            ctes: Some(vec![Cte {
+                name: "v".to_owned(),
+                select: malformed,
+            }]),
+            ..Default::default()
+        };
+        assert_eq!(
+            with_cte.validate(),
+            Err(ExpressionError::LeadingConjunction)
+        );
+
+        assert_eq!(Expression::validate(&[cond(), and(), cond()]), Ok(()));
+    }
+
+    #[test]
+    fn join() {
+        let selection = Select {
+            table: "orders".into(),
+            columns: vec![
+                ColumnAlias::Simple("orders.id".to_owned()),
+                ColumnAlias::Alias(("users.name".to_owned(), "user".to_owned())),
+            ],
+            joins: Some(vec![Join {
+                table: "users".into(),
+                join_type: JoinType::Left,
+                on: vec![JoinOn {
+                    left: "orders.user_id".to_owned(),
+                    right: "users.id".to_owned(),
+                }],
+            }]),
+            ..Default::default()
+        };
+
+        let cvt = serde_json::to_string(&selection).unwrap();
+        let res = "{\"table\":\"orders\",\"columns\":[\"orders.id\",[\"users.name\",\"user\"]],\"windows\":null,\"joins\":[{\"table\":\"users\",\"join_type\":\"Left\",\"on\":[{\"left\":\"orders.user_id\",\"right\":\"users.id\"}]}],\"filter\":null,\"order\":null,\"limit\":null,\"offset\":null,\"ctes\":null}";
+
+        assert_eq!(cvt, res);
+        assert_eq!(serde_json::from_str::<Select>(res).unwrap(), selection);
+        assert_eq!(selection.validate(), Ok(()));
+
+        let mut missing_on = selection;
+        missing_on.joins.as_mut().unwrap()[0].on.clear();
+        assert_eq!(missing_on.validate(), Err(ExpressionError::EmptyJoinOn));
+    }
+
+    #[test]
+    fn self_join() {
+        let on = |left: &str, right: &str| {
+            vec![JoinOn {
+                left: left.to_owned(),
+                right: right.to_owned(),
+            }]
+        };
+        let latest_login = Select {
+            table: "logins".into(),
+            columns: vec![
+                ColumnAlias::Simple("user_id".to_owned()),
+                ColumnAlias::Expr("max(ts) AS ts".to_owned()),
+            ],
+            ..Default::default()
+        };
+        let selection = Select {
+            table: SelectFrom::aliased("docs", "d"),
+            columns: vec![
+                ColumnAlias::Alias(("c.name".to_owned(), "created_by".to_owned())),
+                ColumnAlias::Alias(("u.name".to_owned(), "updated_by".to_owned())),
+                ColumnAlias::Alias(("l.ts".to_owned(), "last_login".to_owned())),
+            ],
+            joins: Some(vec![
+                Join {
+                    table: SelectFrom::aliased("users", "c"),
+                    join_type: JoinType::Inner,
+                    on: on("d.created_by", "c.id"),
+                },
+                Join {
+                    table: SelectFrom::aliased("users", "u"),
+                    join_type: JoinType::Left,
+                    on: on("d.updated_by", "u.id"),
+                },
+                Join {
+                    table: SelectFrom::Subquery {
+                        select: Box::new(latest_login),
+                        alias: "l".to_owned(),
+                    },
+                    join_type: JoinType::Left,
+                    on: on("d.updated_by", "l.user_id"),
+                },
+            ]),
+            ..Default::default()
+        };
+
+        let cvt = serde_json::to_string(&selection).unwrap();
+
+        assert!(cvt.starts_with("{\"table\":{\"table\":\"docs\",\"alias\":\"d\"}"));
+        assert!(
+            cvt.contains("{\"table\":{\"table\":\"users\",\"alias\":\"u\"},\"join_type\":\"Left\"")
+        );
+        assert!(cvt.contains("{\"table\":{\"select\":{\"table\":\"logins\""));
+        assert_eq!(serde_json::from_str::<Select>(&cvt).unwrap(), selection);
+        assert_eq!(selection.validate(), Ok(()));
+    }
+
+    #[test]
+    fn subquery() {
+        let inner = Select {
+            table: "vip".into(),
+            columns: vec![ColumnAlias::Simple("id".to_owned())],
+            filter: Some(Expr::col("level").ge(3).build().unwrap()),
+            ..Default::default()
+        };
+
+        let mut selection = Select::from_subquery(
+            Select::from_subquery(inner.clone(), "v", vec![]),
+            "t",
+            vec![ColumnAlias::Simple("id".to_owned())],
+        );
+        selection.filter = Some(Expr::col("id").in_subquery(inner).build().unwrap());
+
+        let cvt = serde_json::to_string(&selection).unwrap();
+
+        assert_eq!(serde_json::from_str::<Select>(&cvt).unwrap(), selection);
+        assert!(cvt.contains("{\"InSubquery\":{\"table\":\"vip\""));
+        assert!(cvt.starts_with("{\"table\":{\"select\":{\"table\":{\"select\""));
+        assert!(cvt.contains("\"alias\":\"v\""));
+
+        let qualified = Select {
+            table: TableRef::with_schema("crm", "vip").into(),
+            ..Default::default()
+        };
+        let cvt = serde_json::to_string(&qualified).unwrap();
+
+        assert!(cvt.starts_with("{\"table\":{\"schema\":\"crm\",\"name\":\"vip\"}"));
+        assert_eq!(serde_json::from_str::<Select>(&cvt).unwrap(), qualified);
+
+        let stray_name =
+            "{\"select\":{\"table\":\"vip\",\"columns\":[]},\"alias\":\"t\",\"name\":\"x\"}";
+        assert_eq!(
+            serde_json::from_str::<SelectFrom>(stray_name).unwrap(),
+            SelectFrom::Subquery {
+                select: Box::new(Select {
+                    table: "vip".into(),
+                    ..Default::default()
+                }),
+                alias: "t".to_owned(),
+            }
+        );
+        let stray_alias = "{\"schema\":\"crm\",\"name\":\"vip\",\"alias\":\"t\"}";
+        assert!(serde_json::from_str::<SelectFrom>(stray_alias).is_err());
+    }
+
+    #[test]
+    fn null_and_negation() {
+        let filter = Expr::col("c1")
+            .is_null()
+            .or(Expr::col("c2").is_not_null())
+            .or(Expr::col("c3").not_in(vec![1, 2]))
+            .or(Expr::col("c4").not_like("J%"))
+            .build()
+            .unwrap();
+
+        let cvt = serde_json::to_string(&filter).unwrap();
+        let res = "[{\"column\":\"c1\",\"equation\":\"IsNull\"},\"OR\",{\"column\":\"c2\",\"equation\":\"IsNotNull\"},\"OR\",{\"column\":\"c3\",\"equation\":{\"NotIn\":[1,2]}},\"OR\",{\"column\":\"c4\",\"equation\":{\"NotLike\":\"J%\"}}]";
+
+        assert_eq!(cvt, res);
+        assert_eq!(
+            serde_json::from_str::<Vec<Expression>>(res).unwrap(),
+            filter
+        );
+    }
+
+    #[test]
+    fn raw_fragment() {
+        let selection = Select {
+            table: "sales".into(),
+            columns: vec![
+                ColumnAlias::Simple("id".to_owned()),
+                ColumnAlias::Expr("price * qty AS total".to_owned()),
+            ],
+            filter: Some(
+                Expr::col("qty")
+                    .gt(0)
+                    .and(Expr::raw("date_trunc('day', ts) = CURRENT_DATE"))
+                    .build()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        };
+
+        let cvt = serde_json::to_string(&selection).unwrap();
+        let res = "{\"table\":\"sales\",\"columns\":[\"id\",{\"Expr\":\"price * qty AS total\"}],\"windows\":null,\"joins\":null,\"filter\":[{\"column\":\"qty\",\"equation\":{\"Greater\":0}},\"AND\",[{\"Raw\":\"date_trunc('day', ts) = CURRENT_DATE\"}]],\"order\":null,\"limit\":null,\"offset\":null,\"ctes\":null}";
+
+        assert_eq!(cvt, res);
+        assert_eq!(serde_json::from_str::<Select>(res).unwrap(), selection);
+    }
+
+    #[test]
+    fn window() {
+        let latest = Select {
+            table: "prices".into(),
+            columns: vec![
+                ColumnAlias::Simple("ticker".to_owned()),
+                ColumnAlias::Simple("close".to_owned()),
+            ],
+            windows: Some(vec![
+                Window {
+                    function: WindowFunction::RowNumber,
+                    partition_by: vec!["ticker".to_owned()],
+                    order_by: vec![Order {
+                        name: "date".to_owned(),
+                        order: Some(OrderType::Desc),
+                    }],
+                    alias: "rn".to_owned(),
+                },
+                Window {
+                    function: WindowFunction::Lag {
+                        column: "close".to_owned(),
+                        offset: Some(5),
+                        default: Some(DataEnum::Float(0.0)),
+                    },
+                    partition_by: vec!["ticker".to_owned()],
+                    order_by: vec![Order {
+                        name: "date".to_owned(),
+                        order: None,
+                    }],
+                    alias: "close_5d".to_owned(),
+                },
+            ]),
+            ..Default::default()
+        };
+
+        let mut selection = Select::from_subquery(
+            latest,
+            "t",
+            vec![
+                ColumnAlias::Simple("ticker".to_owned()),
+                ColumnAlias::Simple("close".to_owned()),
+            ],
+        );
+        selection.filter = Some(Expr::col("rn").eq(1).build().unwrap());
+
+        let cvt = serde_json::to_string(&selection).unwrap();
+
+        assert!(cvt.contains(
+            "{\"function\":{\"Lag\":{\"column\":\"close\",\"offset\":5,\"default\":0.0}}"
+        ));
+        assert!(cvt.contains("\"windows\":[{\"function\":\"RowNumber\",\"partition_by\":[\"ticker\"],\"order_by\":[{\"name\":\"date\",\"order\":\"Desc\"}],\"alias\":\"rn\"},"));
+        assert_eq!(serde_json::from_str::<Select>(&cvt).unwrap(), selection);
+    }
+
+    #[test]
+    fn cte() {
+        let daily = Select {
+            table: "trades".into(),
+            columns: vec![
+                ColumnAlias::Simple("ticker".to_owned()),
+                ColumnAlias::Expr("sum(qty) AS volume".to_owned()),
+            ],
+            ..Default::default()
+        };
+
+        let selection = Select {
+            table: "daily".into(),
+            columns: vec![ColumnAlias::Simple("ticker".to_owned())],
+            filter: Some(Expr::col("volume").gt(1000).build().unwrap()),
+            ctes: Some(vec![Cte {
+                name: "daily".to_owned(),
+                select: daily,
+            }]),
+            ..Default::default()
+        };
+
+        let cvt = serde_json::to_string(&selection).unwrap();
+
+        assert!(cvt.contains("\"ctes\":[{\"name\":\"daily\",\"select\":{\"table\":\"trades\""));
+        assert_eq!(serde_json::from_str::<Select>(&cvt).unwrap(), selection);
+    }
+
+    #[test]
+    fn pagination() {
+        let selection = Select {
+            table: "events".into(),
+            columns: vec![ColumnAlias::Simple("id".to_owned())],
+            filter: Some(
+                Expr::col("kind")
+                    .eq("click")
+                    .or(Expr::col("kind").eq("view"))
+                    .build()
+                    .unwrap(),
+            ),
+            offset: Some(40),
+            ..Default::default()
+        };
+
+        let first = selection.paginate_after("id", None, 20);
+        assert_eq!(first.filter, selection.filter);
+        assert_eq!(first.limit, Some(20));
+        assert_eq!(first.offset, None);
+
+        let next = selection.paginate_after("id", Some(DataEnum::Integer(120)), 20);
+        let expected = ExpressionBuilder::from(selection.filter.clone().unwrap())
+            .and(Expr::col("id").gt(120))
+            .build()
+            .unwrap();
+        assert_eq!(next.filter, Some(expected));
+        assert!(matches!(
+            next.filter.as_ref().unwrap()[0],
+            Expression::Nest(_)
+        ));
+        assert_eq!(
+            next.order,
+            Some(vec![Order {
+                name: "id".to_owned(),
+                order: Some(OrderType::Asc),
+            }])
+        );
+
+        let raw = Select {
+            table: "events".into(),
+            filter: Some(vec![Expression::Raw(
+                "kind = 'click' OR kind = 'view'".to_owned(),
+            )]),
+            order: Some(vec![Order {
+                name: "ts".to_owned(),
+                order: Some(OrderType::Desc),
+            }]),
+            ..Default::default()
+        };
+        let next = raw.paginate_after("id", Some(DataEnum::Integer(120)), 20);
+        assert_eq!(
+            next.filter,
+            Some(vec![
+                Expression::Nest(raw.filter.clone().unwrap()),
+                Expression::Conjunction(Conjunction::AND),
+                Expression::Simple(Condition {
+                    column: "id".to_owned(),
+                    equation: Equation::Greater(DataEnum::Integer(120)),
+                }),
+            ])
+        );
+        assert_eq!(next.order.unwrap()[0].name, "id");
+
+        let empty = Select {
+            table: "events".into(),
+            filter: Some(vec![]),
+            ..Default::default()
+        };
+        let first = empty.paginate_after("id", None, 20);
+        assert_eq!(first.filter, None);
+        assert_eq!(first.validate(), Ok(()));
+        let next = empty.paginate_after("id", Some(DataEnum::Integer(120)), 20);
+        assert_eq!(next.filter, Some(Expr::col("id").gt(120).build().unwrap()));
+        assert_eq!(next.validate(), Ok(()));
+    }
+
+    #[test]
+    fn raw_grouping() {
+        let raw = || Expression::Raw("a = 1 OR b = 2".to_owned());
+        let id = Expression::Simple(Condition {
+            column: "id".to_owned(),
+            equation: Equation::Greater(DataEnum::Integer(5)),
+        });
+
+        let filter = Expr::raw("a = 1 OR b = 2")
+            .and(Expr::col("id").gt(5))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            filter,
+            vec![
+                Expression::Nest(vec![raw()]),
+                Expression::Conjunction(Conjunction::AND),
+                id,
+            ]
+        );
+    }
 }
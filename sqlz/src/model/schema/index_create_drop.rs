@@ -1,11 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-use super::super::Index;
+use super::super::{Index, TableRef};
 
 pub type IndexCreate = Index;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct IndexDrop {
     pub name: String,
-    pub table: String,
+    pub table: TableRef,
 }
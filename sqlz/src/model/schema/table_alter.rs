@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::super::Column;
+use super::super::{Column, TableRef};
 
 pub type ColumnAdd = Column;
 
@@ -27,7 +27,7 @@ pub enum ColumnAlterCase {
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct TableAlter {
-    pub name: String,
+    pub name: TableRef,
     pub alter: Vec<ColumnAlterCase>,
 }
 
@@ -39,7 +39,7 @@ mod tests_table_alter {
     #[test]
     fn test_table_alter() {
         let table_alter = TableAlter {
-            name: "test".to_string(),
+            name: "test".into(),
             alter: vec![
                 ColumnAlterCase::Add(ColumnAdd {
                     name: "score".to_string(),
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+use super::super::TableRef;
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct TableDrop {
-    pub name: String,
+    pub name: TableRef,
 }
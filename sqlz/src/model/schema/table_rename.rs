@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use super::super::TableRef;
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct TableRename {
-    pub from: String,
-    pub to: String,
+    pub from: TableRef,
+    pub to: TableRef,
 }